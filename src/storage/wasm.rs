@@ -1,6 +1,7 @@
 use crate::counter::Counter;
 use crate::limit::Limit;
 use crate::storage::{Storage, StorageErr};
+use std::cell::Cell;
 use std::collections::{HashMap, HashSet};
 use std::hash::Hash;
 use std::iter::FromIterator;
@@ -18,6 +19,7 @@ pub trait Clock: Sync + Send {
 pub struct CacheEntry<V> {
     pub value: V,
     pub expires_at: SystemTime,
+    age: Cell<u8>,
 }
 
 impl<V: Copy> CacheEntry<V> {
@@ -26,28 +28,80 @@ impl<V: Copy> CacheEntry<V> {
     }
 }
 
+/// A key-value cache with an optional bound on the number of entries it can
+/// hold, used as the backing store for WASM hosts that can't run a
+/// background thread to reclaim memory. When bounded, `insert` evicts an
+/// entry inline: first any already-expired entry, otherwise the
+/// least-recently touched one, tracked via a wrapping `age` counter that the
+/// host advances with `bump_age()` on each flush tick.
 pub struct Cache<K: Eq + Hash, V: Copy> {
     pub map: HashMap<K, CacheEntry<V>>,
+    max_entries: Option<usize>,
+    age: u8,
 }
 
 impl<K: Eq + Hash + Clone, V: Copy> Cache<K, V> {
     pub fn new() -> Self {
         Self {
             map: HashMap::new(),
+            max_entries: None,
+            age: 0,
+        }
+    }
+
+    pub fn with_capacity(max_entries: usize) -> Self {
+        Self {
+            map: HashMap::new(),
+            max_entries: Some(max_entries),
+            age: 0,
         }
     }
 
     pub fn get(&self, key: &K) -> Option<&CacheEntry<V>> {
-        self.map.get(&key)
+        let entry = self.map.get(key)?;
+        entry.age.set(self.age);
+        Some(entry)
     }
 
     pub fn get_mut(&mut self, key: &K) -> Option<&mut CacheEntry<V>> {
-        self.map.get_mut(&key)
+        let age = self.age;
+        let entry = self.map.get_mut(key)?;
+        entry.age.set(age);
+        Some(entry)
     }
 
-    pub fn insert(&mut self, key: &K, value: V, expires_at: SystemTime) {
-        self.map
-            .insert(key.clone(), CacheEntry { value, expires_at });
+    /// Inserts `key` -> `value`, evicting inline if the cache is at
+    /// capacity. Returns the evicted key, if any, so callers that keep a
+    /// secondary index over the same keys (e.g. `WasmStorage`'s
+    /// `limits_for_namespace`) can prune it in lockstep instead of letting
+    /// it grow unbounded alongside a bounded cache.
+    pub fn insert(
+        &mut self,
+        key: &K,
+        value: V,
+        expires_at: SystemTime,
+        current_time: SystemTime,
+    ) -> Option<K> {
+        let evicted = if let Some(max_entries) = self.max_entries {
+            if !self.map.contains_key(key) && self.map.len() >= max_entries {
+                self.evict(current_time)
+            } else {
+                None
+            }
+        } else {
+            None
+        };
+
+        self.map.insert(
+            key.clone(),
+            CacheEntry {
+                value,
+                expires_at,
+                age: Cell::new(self.age),
+            },
+        );
+
+        evicted
     }
 
     pub fn remove(&mut self, key: &K) {
@@ -63,6 +117,34 @@ impl<K: Eq + Hash + Clone, V: Copy> Cache<K, V> {
 
         Vec::from_iter(iterator)
     }
+
+    /// Advances the global age counter, wrapping on overflow. The host
+    /// drives this on each flush tick; entries left behind become
+    /// eviction candidates the next time `insert` needs room.
+    pub fn bump_age(&mut self) {
+        self.age = self.age.wrapping_add(1);
+    }
+
+    fn evict(&mut self, current_time: SystemTime) -> Option<K> {
+        let expired = self
+            .map
+            .iter()
+            .find(|(_, entry)| entry.is_expired(current_time))
+            .map(|(key, _)| key.clone());
+
+        let victim = expired.or_else(|| {
+            self.map
+                .iter()
+                .max_by_key(|(_, entry)| self.age.wrapping_sub(entry.age.get()))
+                .map(|(key, _)| key.clone())
+        });
+
+        if let Some(key) = &victim {
+            self.map.remove(key);
+        }
+
+        victim
+    }
 }
 
 impl<K: Eq + Hash + Clone, V: Copy> Default for Cache<K, V> {
@@ -71,9 +153,107 @@ impl<K: Eq + Hash + Clone, V: Copy> Default for Cache<K, V> {
     }
 }
 
+/// Parameters derived from a `Limit` that opts into the GCRA (leaky bucket)
+/// strategy via `Limit::gcra_burst`, as an alternative to the default fixed
+/// window. `emission_interval` is the steady-state spacing between actions
+/// (period / n) and `delay_variation_tolerance` is how far the TAT may run
+/// ahead of "now" before a request is rejected, given the configured burst.
+struct GcraParams {
+    emission_interval: Duration,
+    delay_variation_tolerance: Duration,
+}
+
+impl GcraParams {
+    fn for_counter(counter: &Counter) -> Option<Self> {
+        let burst = counter.limit().gcra_burst()?;
+        let n = counter.max_value();
+        if n <= 0 {
+            return None;
+        }
+
+        let emission_interval = duration_div_u64(Duration::from_secs(counter.seconds()), n as u64);
+        if emission_interval.is_zero() {
+            return None;
+        }
+
+        Some(Self {
+            emission_interval,
+            delay_variation_tolerance: emission_interval * (burst as u32 + 1),
+        })
+    }
+}
+
+/// Divides `duration` by `divisor` via nanosecond math instead of
+/// `Duration`'s built-in `Div<u32>`, so a `max_value` above `u32::MAX`
+/// shrinks the emission interval correctly rather than silently
+/// truncating `divisor` down to a much smaller `u32` and over-restricting
+/// the configured limit.
+fn duration_div_u64(duration: Duration, divisor: u64) -> Duration {
+    let nanos = duration.as_nanos() / divisor as u128;
+    Duration::from_nanos(nanos.min(u64::MAX as u128) as u64)
+}
+
+/// Multiplies `duration` by `factor` via nanosecond math instead of
+/// `Duration`'s built-in `Mul<u32>`, so a `quantity` that's an exact
+/// multiple of 2^32 doesn't truncate to an increment of 0 and bypass the
+/// leaky bucket for free.
+fn duration_mul_u64(duration: Duration, factor: u64) -> Duration {
+    let nanos = duration.as_nanos().saturating_mul(factor as u128);
+    Duration::from_nanos(nanos.min(u64::MAX as u128) as u64)
+}
+
+struct GcraResult {
+    now: SystemTime,
+    allowed: bool,
+    new_tat: SystemTime,
+    remaining: i64,
+    retry_after: Duration,
+}
+
+/// The GCRA decision itself, kept free of `WasmStorage`/`Counter` so it can
+/// be driven straight off a stored TAT (or the lack of one). Gates on the
+/// *post*-increment TAT: a request is only allowed if admitting it would
+/// not push the TAT past the tolerance, so an oversized `quantity` is
+/// rejected on its own merits rather than only being checked against the
+/// pre-existing backlog.
+fn gcra_decide(
+    now: SystemTime,
+    stored_tat: Option<SystemTime>,
+    quantity: i64,
+    params: &GcraParams,
+) -> GcraResult {
+    let tat = stored_tat.unwrap_or(now).max(now);
+    let increment = duration_mul_u64(params.emission_interval, quantity.max(0) as u64);
+    let new_tat = tat + increment;
+    let new_tat_ahead_of_now = new_tat.duration_since(now).unwrap_or_default();
+
+    if new_tat_ahead_of_now > params.delay_variation_tolerance {
+        return GcraResult {
+            now,
+            allowed: false,
+            new_tat,
+            remaining: 0,
+            retry_after: new_tat_ahead_of_now - params.delay_variation_tolerance,
+        };
+    }
+
+    let remaining = ((params.delay_variation_tolerance - new_tat_ahead_of_now).as_secs_f64()
+        / params.emission_interval.as_secs_f64())
+    .floor() as i64;
+
+    GcraResult {
+        now,
+        allowed: true,
+        new_tat,
+        remaining,
+        retry_after: new_tat_ahead_of_now,
+    }
+}
+
 pub struct WasmStorage {
     limits_for_namespace: HashMap<String, HashMap<Limit, HashSet<Counter>>>,
     pub counters: Cache<Counter, i64>,
+    gcra_tats: Cache<Counter, SystemTime>,
     pub clock: Box<dyn Clock>,
 }
 
@@ -121,6 +301,10 @@ impl Storage for WasmStorage {
     }
 
     fn is_within_limits(&self, counter: &Counter, delta: i64) -> Result<bool, StorageErr> {
+        if let Some(params) = GcraParams::for_counter(counter) {
+            return Ok(self.gcra_check(counter, delta, &params).allowed);
+        }
+
         let within_limits = match self.counters.get(counter) {
             Some(entry) => {
                 if entry.is_expired(self.clock.get_current_time()) {
@@ -136,25 +320,39 @@ impl Storage for WasmStorage {
     }
 
     fn update_counter(&mut self, counter: &Counter, delta: i64) -> Result<(), StorageErr> {
+        if let Some(params) = GcraParams::for_counter(counter) {
+            let result = self.gcra_check(counter, delta, &params);
+            if result.allowed {
+                self.gcra_commit(counter, &result, &params);
+            }
+            return Ok(());
+        }
+
         match self.counters.get_mut(counter) {
             Some(entry) => {
                 if entry.is_expired(self.clock.get_current_time()) {
                     // TODO: remove duplication. "None" branch is identical.
-                    self.counters.insert(
+                    let now = self.clock.get_current_time();
+                    let evicted = self.counters.insert(
                         counter,
                         counter.max_value() - delta,
-                        self.clock.get_current_time() + Duration::from_secs(counter.seconds()),
+                        now + Duration::from_secs(counter.seconds()),
+                        now,
                     );
+                    self.remove_counter_limit_association(evicted);
                 } else {
                     entry.value -= delta;
                 }
             }
             None => {
-                self.counters.insert(
+                let now = self.clock.get_current_time();
+                let evicted = self.counters.insert(
                     counter,
                     counter.max_value() - delta,
-                    self.clock.get_current_time() + Duration::from_secs(counter.seconds()),
+                    now + Duration::from_secs(counter.seconds()),
+                    now,
                 );
+                self.remove_counter_limit_association(evicted);
 
                 self.add_counter_limit_association(counter);
             }
@@ -167,25 +365,15 @@ impl Storage for WasmStorage {
         &mut self,
         namespace: &str,
     ) -> Result<Vec<(Counter, i64, Duration)>, StorageErr> {
-        // TODO: optimize to avoid iterating over all of them.
+        let now = self.clock.get_current_time();
 
-        Ok(self
-            .counters
-            .get_all(self.clock.get_current_time())
-            .iter()
-            .filter(|(counter, _, _)| counter.namespace() == namespace)
-            .map(|(counter, value, expires_at)| {
-                (
-                    counter.clone(),
-                    *value,
-                    expires_at.duration_since(SystemTime::UNIX_EPOCH).unwrap()
-                        - self
-                            .clock
-                            .get_current_time()
-                            .duration_since(SystemTime::UNIX_EPOCH)
-                            .unwrap(),
-                )
-            })
+        let counters = match self.limits_for_namespace.get(namespace) {
+            Some(counters_by_limit) => counters_by_limit.values().flatten(),
+            None => return Ok(Vec::new()),
+        };
+
+        Ok(counters
+            .filter_map(|counter| self.counter_state(counter, now))
             .collect())
     }
 }
@@ -195,18 +383,103 @@ impl WasmStorage {
         Self {
             limits_for_namespace: HashMap::new(),
             counters: Cache::default(),
+            gcra_tats: Cache::default(),
+            clock,
+        }
+    }
+
+    /// Builds a `WasmStorage` whose counter caches are capped at
+    /// `max_entries` each, so a long-running WASM host has a predictable
+    /// memory ceiling instead of growing unbounded. The host should call
+    /// [`WasmStorage::bump_age`] on a flush tick so the caches' inline
+    /// eviction can tell recently-touched entries from stale ones. Evicted
+    /// counters are also pruned from `limits_for_namespace`, so the
+    /// namespace index stays bounded by the caches rather than retaining
+    /// every distinct counter key ever seen.
+    pub fn with_capacity(clock: Box<impl Clock + 'static>, max_entries: usize) -> Self {
+        Self {
+            limits_for_namespace: HashMap::new(),
+            counters: Cache::with_capacity(max_entries),
+            gcra_tats: Cache::with_capacity(max_entries),
             clock,
         }
     }
 
+    /// Advances the age of both counter caches. The host drives this once
+    /// per flush tick; entries left untouched since become the next
+    /// eviction candidates when a bounded cache needs room.
+    pub fn bump_age(&mut self) {
+        self.counters.bump_age();
+        self.gcra_tats.bump_age();
+    }
+
+    fn gcra_check(&self, counter: &Counter, quantity: i64, params: &GcraParams) -> GcraResult {
+        let now = self.clock.get_current_time();
+        let stored_tat = self
+            .gcra_tats
+            .get(counter)
+            .filter(|entry| !entry.is_expired(now))
+            .map(|entry| entry.value);
+
+        gcra_decide(now, stored_tat, quantity, params)
+    }
+
+    /// Persists the TAT computed by a prior, already-allowed `gcra_check`
+    /// result, reusing its `now`/`new_tat` rather than re-deriving them
+    /// from the cache so check and commit can never disagree.
+    fn gcra_commit(&mut self, counter: &Counter, result: &GcraResult, params: &GcraParams) {
+        let evicted = self.gcra_tats.insert(
+            counter,
+            result.new_tat,
+            result.new_tat + params.delay_variation_tolerance,
+            result.now,
+        );
+        self.remove_counter_limit_association(evicted);
+        self.add_counter_limit_association(counter);
+    }
+
+    /// Looks up the current state of a single counter, whichever cache it
+    /// lives in, returning `None` if it has no entry yet or has expired.
+    fn counter_state(&self, counter: &Counter, now: SystemTime) -> Option<(Counter, i64, Duration)> {
+        if let Some(params) = GcraParams::for_counter(counter) {
+            let entry = self.gcra_tats.get(counter)?;
+            if entry.is_expired(now) {
+                return None;
+            }
+
+            // Quantity 0: read the current standing of the TAT without
+            // advancing it, reusing the same decision logic `gcra_check`
+            // uses for live requests.
+            let result = gcra_decide(now, Some(entry.value), 0, &params);
+            return Some((counter.clone(), result.remaining, result.retry_after));
+        }
+
+        let entry = self.counters.get(counter)?;
+        if entry.is_expired(now) {
+            return None;
+        }
+
+        let expires_in = entry
+            .expires_at
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            - now.duration_since(SystemTime::UNIX_EPOCH).unwrap();
+
+        Some((counter.clone(), entry.value, expires_in))
+    }
+
     pub fn add_counter(&mut self, counter: &Counter, value: i64, expires_at: SystemTime) {
-        self.counters.insert(counter, value, expires_at);
+        let now = self.clock.get_current_time();
+        let evicted = self.counters.insert(counter, value, expires_at, now);
+        self.remove_counter_limit_association(evicted);
+        self.add_counter_limit_association(counter);
     }
 
     fn delete_counters_in_namespace(&mut self, namespace: &str) {
         if let Some(counters_by_limit) = self.limits_for_namespace.get(namespace) {
             for counter in counters_by_limit.values().flatten() {
                 self.counters.remove(counter);
+                self.gcra_tats.remove(counter);
             }
         }
     }
@@ -216,6 +489,7 @@ impl WasmStorage {
             if let Some(counters) = counters_by_limit.get(limit) {
                 for counter in counters.iter() {
                     self.counters.remove(counter);
+                    self.gcra_tats.remove(counter);
                 }
             }
         }
@@ -231,4 +505,301 @@ impl WasmStorage {
                 .insert(counter.clone());
         }
     }
+
+    /// Drops a counter evicted from a bounded `Cache` out of
+    /// `limits_for_namespace` too, so the per-limit `HashSet<Counter>`
+    /// doesn't keep growing once its backing cache entry is gone -- without
+    /// this, a bounded cache still leaks one index entry per distinct
+    /// counter key ever seen over the life of the process.
+    fn remove_counter_limit_association(&mut self, evicted: Option<Counter>) {
+        let Some(counter) = evicted else {
+            return;
+        };
+
+        if let Some(counters_by_limit) = self.limits_for_namespace.get_mut(counter.limit().namespace())
+        {
+            if let Some(counters) = counters_by_limit.get_mut(counter.limit()) {
+                counters.remove(&counter);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    struct TestClock(Mutex<SystemTime>);
+
+    impl TestClock {
+        fn new(start: SystemTime) -> Self {
+            Self(Mutex::new(start))
+        }
+    }
+
+    impl Clock for TestClock {
+        fn get_current_time(&self) -> SystemTime {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    fn gcra_params(n: u32, period: Duration, burst: u32) -> GcraParams {
+        let emission_interval = period / n;
+        GcraParams {
+            emission_interval,
+            delay_variation_tolerance: emission_interval * (burst + 1),
+        }
+    }
+
+    #[test]
+    fn gcra_allows_exactly_one_request_with_zero_burst() {
+        // n=10/10s, burst=0: a request's own size is checked against the
+        // limit, so the second back-to-back request of the same size must
+        // be rejected rather than sneaking through on a stale pre-increment
+        // check.
+        let params = gcra_params(10, Duration::from_secs(10), 0);
+        let now = SystemTime::UNIX_EPOCH;
+
+        let first = gcra_decide(now, None, 1, &params);
+        assert!(first.allowed);
+
+        let second = gcra_decide(now, Some(first.new_tat), 1, &params);
+        assert!(!second.allowed);
+    }
+
+    #[test]
+    fn gcra_rejects_a_single_oversized_request_against_an_empty_backlog() {
+        let params = gcra_params(10, Duration::from_secs(10), 0);
+        let now = SystemTime::UNIX_EPOCH;
+
+        let result = gcra_decide(now, None, 2, &params);
+        assert!(!result.allowed);
+    }
+
+    #[test]
+    fn gcra_allows_bursts_up_to_the_configured_tolerance_and_then_rejects() {
+        let params = gcra_params(10, Duration::from_secs(10), 2);
+        let now = SystemTime::UNIX_EPOCH;
+
+        let mut tat = None;
+        for _ in 0..3 {
+            let result = gcra_decide(now, tat, 1, &params);
+            assert!(result.allowed);
+            tat = Some(result.new_tat);
+        }
+
+        assert!(!gcra_decide(now, tat, 1, &params).allowed);
+    }
+
+    #[test]
+    fn gcra_rejects_a_quantity_thats_an_exact_multiple_of_2_32_instead_of_going_free() {
+        // A `quantity as u32` cast truncates 2^32 down to 0, which would
+        // make the increment free and let the request through unchecked.
+        let params = gcra_params(1, Duration::from_secs(1), 0);
+        let now = SystemTime::UNIX_EPOCH;
+
+        let result = gcra_decide(now, None, 1i64 << 32, &params);
+        assert!(!result.allowed);
+    }
+
+    #[test]
+    fn for_counter_honors_max_value_above_u32_max_instead_of_truncating_it() {
+        // `n as u32` would truncate this `max_value` down to 1, collapsing
+        // the emission interval to the whole period instead of dividing it
+        // by the configured (much larger) `n`.
+        let n = u32::MAX as i64 + 2;
+        let limit = test_gcra_limit("ns", n, n as u64, 0);
+        let counter = Counter::new(limit, HashMap::new());
+
+        let params = GcraParams::for_counter(&counter).unwrap();
+        assert_eq!(params.emission_interval, Duration::from_secs(1));
+    }
+
+    #[test]
+    fn cache_evicts_expired_entries_before_touching_fresh_ones() {
+        let now = SystemTime::UNIX_EPOCH;
+        let mut cache = Cache::with_capacity(2);
+
+        cache.insert(&"expired", 1, now, now);
+        cache.insert(&"fresh", 2, now + Duration::from_secs(60), now);
+
+        let later = now + Duration::from_secs(1);
+        cache.insert(&"new", 3, later + Duration::from_secs(60), later);
+
+        assert!(cache.get(&"expired").is_none());
+        assert!(cache.get(&"fresh").is_some());
+        assert!(cache.get(&"new").is_some());
+    }
+
+    #[test]
+    fn cache_evicts_the_least_recently_touched_entry_with_age_wraparound() {
+        let now = SystemTime::UNIX_EPOCH;
+        let far_future = now + Duration::from_secs(3600);
+        let mut cache = Cache::with_capacity(2);
+
+        // Push the age counter close to wrapping before either entry is
+        // inserted, so the current age ends up numerically *smaller* than
+        // both entries' ages once it wraps past `u8::MAX` - only a
+        // wrapping distance, not plain subtraction, tells them apart.
+        for _ in 0..250 {
+            cache.bump_age();
+        }
+        cache.insert(&"a", 1, far_future, now);
+        for _ in 0..3 {
+            cache.bump_age();
+        }
+        cache.insert(&"b", 2, far_future, now);
+        for _ in 0..5 {
+            cache.bump_age();
+        }
+
+        cache.insert(&"c", 3, far_future, now);
+
+        assert!(cache.get(&"a").is_none());
+        assert!(cache.get(&"b").is_some());
+        assert!(cache.get(&"c").is_some());
+    }
+
+    fn test_limit(namespace: &str, max_value: i64, seconds: u64) -> Limit {
+        Limit::new(
+            namespace,
+            max_value,
+            seconds,
+            Vec::<String>::new(),
+            Vec::<String>::new(),
+        )
+    }
+
+    fn test_gcra_limit(namespace: &str, max_value: i64, seconds: u64, burst: u32) -> Limit {
+        test_limit(namespace, max_value, seconds).with_gcra_burst(burst)
+    }
+
+    #[test]
+    fn gcra_limit_is_enforced_through_update_counter_and_cleared_on_delete() {
+        let clock = TestClock::new(SystemTime::UNIX_EPOCH);
+        let mut storage = WasmStorage::new(Box::new(clock));
+
+        let limit = test_gcra_limit("ns", 2, 10, 0);
+        storage.add_limit(limit.clone()).unwrap();
+        let counter = Counter::new(limit.clone(), HashMap::new());
+
+        assert!(storage.is_within_limits(&counter, 1).unwrap());
+        storage.update_counter(&counter, 1).unwrap();
+
+        assert!(!storage.is_within_limits(&counter, 1).unwrap());
+        assert_eq!(storage.get_counters("ns").unwrap().len(), 1);
+
+        storage.delete_limit(&limit).unwrap();
+
+        assert!(storage.get_counters("ns").unwrap().is_empty());
+        assert!(storage.gcra_tats.get(&counter).is_none());
+    }
+
+    #[test]
+    fn get_counters_is_scoped_to_the_requested_namespace() {
+        let clock = TestClock::new(SystemTime::UNIX_EPOCH);
+        let mut storage = WasmStorage::new(Box::new(clock));
+
+        let limit_a = test_limit("ns1", 10, 60);
+        let limit_b = test_limit("ns2", 5, 60);
+        storage.add_limit(limit_a.clone()).unwrap();
+        storage.add_limit(limit_b.clone()).unwrap();
+
+        storage
+            .update_counter(&Counter::new(limit_a, HashMap::new()), 1)
+            .unwrap();
+        storage
+            .update_counter(&Counter::new(limit_b, HashMap::new()), 2)
+            .unwrap();
+
+        let ns1 = storage.get_counters("ns1").unwrap();
+        assert_eq!(ns1.len(), 1);
+        assert_eq!(ns1[0].1, 9);
+
+        let ns2 = storage.get_counters("ns2").unwrap();
+        assert_eq!(ns2.len(), 1);
+        assert_eq!(ns2[0].1, 3);
+    }
+
+    #[test]
+    fn add_counter_is_visible_through_the_namespace_index() {
+        let clock = TestClock::new(SystemTime::UNIX_EPOCH);
+        let mut storage = WasmStorage::new(Box::new(clock));
+
+        let limit = test_limit("ns", 10, 60);
+        storage.add_limit(limit.clone()).unwrap();
+
+        let counter = Counter::new(limit, HashMap::new());
+        storage.add_counter(&counter, 7, SystemTime::UNIX_EPOCH + Duration::from_secs(60));
+
+        let counters = storage.get_counters("ns").unwrap();
+        assert_eq!(counters.len(), 1);
+        assert_eq!(counters[0].1, 7);
+    }
+
+    fn counter_with_id(limit: &Limit, id: &str) -> Counter {
+        let mut values = HashMap::new();
+        values.insert("id".to_string(), id.to_string());
+        Counter::new(limit.clone(), values)
+    }
+
+    #[test]
+    fn with_capacity_evicts_counters_seeded_through_update_counter_and_add_counter() {
+        let clock = TestClock::new(SystemTime::UNIX_EPOCH);
+        let mut storage = WasmStorage::with_capacity(Box::new(clock), 2);
+
+        let limit = test_limit("ns", 10, 60);
+        storage.add_limit(limit.clone()).unwrap();
+
+        storage
+            .update_counter(&counter_with_id(&limit, "a"), 1)
+            .unwrap();
+        storage
+            .update_counter(&counter_with_id(&limit, "b"), 1)
+            .unwrap();
+        storage.add_counter(
+            &counter_with_id(&limit, "c"),
+            5,
+            SystemTime::UNIX_EPOCH + Duration::from_secs(60),
+        );
+
+        // The cache is capped at 2 entries, so seeding a third counter
+        // through the real `WasmStorage::with_capacity`/`update_counter`/
+        // `add_counter` wiring must have evicted one of the earlier ones
+        // rather than growing past it.
+        assert_eq!(storage.counters.map.len(), 2);
+        assert_eq!(storage.get_counters("ns").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn get_counters_namespace_index_does_not_retain_evicted_counters() {
+        let clock = TestClock::new(SystemTime::UNIX_EPOCH);
+        let mut storage = WasmStorage::with_capacity(Box::new(clock), 1);
+
+        let limit = test_limit("ns", 10, 60);
+        storage.add_limit(limit.clone()).unwrap();
+
+        storage
+            .update_counter(&counter_with_id(&limit, "a"), 1)
+            .unwrap();
+        storage
+            .update_counter(&counter_with_id(&limit, "b"), 1)
+            .unwrap();
+
+        // With capacity 1, inserting "b" evicted "a" from the cache. The
+        // namespace index `get_counters` walks must shrink along with it
+        // instead of retaining "a" forever -- otherwise a long-running,
+        // high-cardinality namespace walks an ever-growing index even
+        // though its live counters stay bounded by the cache, defeating
+        // the point of indexing by namespace.
+        let indexed = storage
+            .limits_for_namespace
+            .get("ns")
+            .unwrap()
+            .get(&limit)
+            .unwrap();
+        assert_eq!(indexed.len(), 1);
+        assert_eq!(storage.get_counters("ns").unwrap().len(), 1);
+    }
 }
\ No newline at end of file